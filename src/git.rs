@@ -0,0 +1,185 @@
+use crate::GitRef;
+use just_core::result::BoxedResult;
+use log::debug;
+use std::path::Path;
+
+/// How many times a shallow clone is attempted before giving up, so a
+/// transient network blip doesn't abort an install outright.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Shallow, single-branch clone of `url` into `dest`, checked out at
+/// `reference`. Returns the commit that ended up checked out.
+pub fn clone_shallow(url: &str, dest: &Path, reference: &GitRef) -> BoxedResult<String> {
+    retry(MAX_ATTEMPTS, || try_clone_shallow(url, dest, reference))
+}
+
+fn try_clone_shallow(url: &str, dest: &Path, reference: &GitRef) -> BoxedResult<String> {
+    if dest.exists() {
+        remove_dir_all::remove_dir_all(dest)?;
+    }
+
+    let repo = git2::Repository::init(dest)?;
+    let mut remote = repo.remote("origin", url)?;
+
+    let commit = match reference {
+        GitRef::Commit(sha) => fetch_commit(&repo, &mut remote, sha)?,
+        _ => fetch_tip(&repo, &mut remote, reference)?,
+    };
+
+    repo.checkout_tree(commit.as_object(), None)?;
+    repo.set_head_detached(commit.id())?;
+
+    Ok(commit.id().to_string())
+}
+
+/// Shallow-fetch the tip of a branch, tag, or `HEAD` and return it.
+fn fetch_tip<'repo>(
+    repo: &'repo git2::Repository,
+    remote: &mut git2::Remote,
+    reference: &GitRef,
+) -> BoxedResult<git2::Commit<'repo>> {
+    let refspec = match reference {
+        GitRef::Default => "HEAD".to_string(),
+        GitRef::Branch(name) => format!("refs/heads/{}", name),
+        GitRef::Tag(name) => format!("refs/tags/{}", name),
+        GitRef::Commit(_) => unreachable!("commits are fetched by fetch_commit"),
+    };
+
+    let mut options = git2::FetchOptions::new();
+    options.depth(1);
+    options.remote_callbacks(credentials_callbacks());
+
+    debug!("Shallow fetch {:?}", refspec);
+    remote.fetch(&[&refspec], Some(&mut options), None)?;
+
+    Ok(repo.find_reference("FETCH_HEAD")?.peel_to_commit()?)
+}
+
+/// Fetch a specific commit. Tries a shallow fetch of the bare SHA first,
+/// which only works against hosts that advertise
+/// `uploadpack.allowReachableSHA1InWant` (GitHub does; most self-hosted
+/// GitLab/Gitea/Bitbucket Server setups don't). If that's rejected, fall
+/// back to a full fetch of the default branch and look the commit up in
+/// its history.
+fn fetch_commit<'repo>(
+    repo: &'repo git2::Repository,
+    remote: &mut git2::Remote,
+    sha: &str,
+) -> BoxedResult<git2::Commit<'repo>> {
+    let mut shallow = git2::FetchOptions::new();
+    shallow.depth(1);
+    shallow.remote_callbacks(credentials_callbacks());
+
+    debug!("Shallow fetch of commit {:?}", sha);
+    if remote.fetch(&[sha], Some(&mut shallow), None).is_err() {
+        debug!(
+            "Remote rejected a shallow fetch of {:?}, falling back to a full fetch",
+            sha
+        );
+
+        let mut full = git2::FetchOptions::new();
+        full.remote_callbacks(credentials_callbacks());
+        remote.fetch(&["HEAD"], Some(&mut full), None)?;
+    }
+
+    let oid = git2::Oid::from_str(sha)?;
+
+    Ok(repo.find_commit(oid)?)
+}
+
+/// SSH-agent credentials first, falling back to the default credential
+/// helper, so authenticated `ssh://`/`git@host:...` and private HTTPS
+/// remotes work the same way they would shelling out to the `git` binary.
+fn credentials_callbacks<'cb>() -> git2::RemoteCallbacks<'cb> {
+    let mut callbacks = git2::RemoteCallbacks::new();
+
+    callbacks.credentials(|url, username_from_url, allowed_types| {
+        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
+            if let Some(username) = username_from_url {
+                if let Ok(cred) = git2::Cred::ssh_key_from_agent(username) {
+                    return Ok(cred);
+                }
+            }
+        }
+
+        if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            if let Ok(config) = git2::Config::open_default() {
+                if let Ok(cred) = git2::Cred::credential_helper(&config, url, username_from_url) {
+                    return Ok(cred);
+                }
+            }
+        }
+
+        git2::Cred::default()
+    });
+
+    callbacks
+}
+
+/// Retry `f` up to `attempts` times, returning the first success or the
+/// last failure if every attempt was exhausted.
+fn retry<T>(attempts: u32, mut f: impl FnMut() -> BoxedResult<T>) -> BoxedResult<T> {
+    let mut last_err = None;
+
+    for attempt in 1..=attempts {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                debug!("Attempt {}/{} failed: {}", attempt, attempts, err);
+                last_err = Some(err);
+            }
+        }
+    }
+
+    Err(last_err.expect("loop runs at least once"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::io::{Error, ErrorKind};
+
+    #[test]
+    fn retry_returns_the_first_success_without_retrying() {
+        let calls = Cell::new(0);
+
+        let result = retry(3, || {
+            calls.set(calls.get() + 1);
+            Ok(calls.get())
+        });
+
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn retry_tries_again_after_a_failure() {
+        let calls = Cell::new(0);
+
+        let result = retry(3, || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 2 {
+                Err(Error::new(ErrorKind::Other, "boom").into())
+            } else {
+                Ok(calls.get())
+            }
+        });
+
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn retry_gives_up_after_exhausting_attempts_and_returns_the_last_error() {
+        let calls = Cell::new(0);
+
+        let result: BoxedResult<()> = retry(3, || {
+            calls.set(calls.get() + 1);
+            Err(Error::new(ErrorKind::Other, format!("attempt {}", calls.get())).into())
+        });
+
+        assert_eq!(calls.get(), 3);
+        assert_eq!(result.unwrap_err().to_string(), "attempt 3");
+    }
+}
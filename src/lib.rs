@@ -1,7 +1,13 @@
+mod git;
+mod manifest;
+mod source;
+
+pub use manifest::{Manifest, ManifestEntry};
+pub use source::GitHost;
+
 use just_core::kernel::Folder;
 use just_core::result::BoxedResult;
 use std::path::PathBuf;
-use url::Url;
 
 pub const JUST_PREFIX: &str = "just-";
 
@@ -16,27 +22,64 @@ fn prepend_just_prefix(name: &str) -> String {
     }
 }
 
-fn is_github_url(url: &Url) -> bool {
-    url.host_str() == Some("github.com")
+fn strip_just_prefix(name: &str) -> String {
+    name.strip_prefix(JUST_PREFIX).unwrap_or(name).to_string()
 }
 
-fn get_repository_name(url: &str) -> BoxedResult<String> {
-    use just_core::result::BoxedErr;
+fn default_scratch_dir(repo: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("just-extension-{}-{}", std::process::id(), repo))
+}
 
-    let url = Url::parse(url)?;
+/// Move `target_path` to `bin_path`, preferring an atomic rename but
+/// falling back to a copy when they're on different filesystems (e.g. a
+/// `scratch_dir` under a tmpfs `/tmp` being swapped into an install
+/// directory on a different mount), where `rename` would fail with `EXDEV`.
+/// The fallback still ends in a same-filesystem `rename` over `bin_path` —
+/// it stages the copy next to `bin_path` first — so the swap itself is
+/// always atomic and a crash mid-copy can't leave a truncated binary at
+/// `bin_path`.
+fn persist_binary(target_path: &std::path::Path, bin_path: &std::path::Path) -> BoxedResult<()> {
+    use std::fs::{copy, remove_file, rename};
 
-    if !is_github_url(&url) {
-        BoxedErr::with("Currently, only github.com is supported for just components")
-    } else if let Some(segments) = url.path_segments() {
-        let vec: Vec<&str> = segments.skip(1).take(1).collect();
-        if let Some(name) = vec.first() {
-            Ok(name.to_string())
-        } else {
-            BoxedErr::with("No repository name in segments")
-        }
-    } else {
-        BoxedErr::with("Invalid URL")
+    if rename(target_path, bin_path).is_ok() {
+        return Ok(());
     }
+
+    let install_dir = bin_path.parent().unwrap_or(bin_path);
+    let file_name = bin_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("just-extension");
+    let staging_path = install_dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    copy(target_path, &staging_path)?;
+    rename(&staging_path, bin_path)?;
+    remove_file(target_path).map_err(|e| e.into())
+}
+
+/// Options controlling where an extension is cloned and built, and what
+/// command name it's installed under.
+#[derive(Default)]
+pub struct InstallOptions {
+    /// Where the repository is cloned and built. Defaults to a unique
+    /// directory under the system temp directory, so an install never
+    /// touches `cwd` or collides with a concurrent one.
+    pub scratch_dir: Option<PathBuf>,
+    /// The command name to install the binary under, if it should differ
+    /// from the repository name, e.g. installing `some-org/fancy-tool` as
+    /// `just-fancy` instead of `just-fancy-tool`.
+    pub alias: Option<String>,
+}
+
+/// A git reference to resolve and check out after cloning an extension's
+/// repository, so an install can be pinned instead of always tracking
+/// whatever the default branch currently points at.
+pub enum GitRef {
+    /// Whatever branch `HEAD` points to on the remote (the previous, implicit behavior).
+    Default,
+    Branch(String),
+    Tag(String),
+    Commit(String),
 }
 
 pub struct Extension<'a> {
@@ -71,24 +114,62 @@ impl<'a> Extension<'a> {
     }
 
     pub fn install(&self, url: &str) -> BoxedResult<()> {
-        use duct::cmd;
+        self.install_ref(url, &GitRef::Default)
+    }
+
+    pub fn install_ref(&self, url: &str, reference: &GitRef) -> BoxedResult<()> {
+        self.install_with(url, reference, &InstallOptions::default())
+    }
+
+    pub fn install_with(
+        &self,
+        url: &str,
+        reference: &GitRef,
+        options: &InstallOptions,
+    ) -> BoxedResult<()> {
         use log::debug;
         use remove_dir_all::remove_dir_all;
         use std::env::consts::EXE_SUFFIX;
-        use std::env::current_dir;
         use std::fs::copy;
 
-        let repo = get_repository_name(url)?;
-        let repo_path = current_dir().expect("Invalid current path").join(&repo);
+        let (repo, repo_path, commit) =
+            self.clone_and_build(url, reference, options.scratch_dir.clone())?;
+        let name = options.alias.clone().unwrap_or_else(|| repo.clone());
+
+        let exe_name = format!("{}{}", repo, EXE_SUFFIX);
+        let target_path = repo_path.join("target").join("release").join(&exe_name);
+        let bin_path = self.assemble_path(&name);
+
+        debug!("Copy {:?} into {:?}", target_path, bin_path);
+
+        copy(&target_path, &bin_path)?;
+        remove_dir_all(&repo_path)?;
+
+        let mut manifest = Manifest::load(&self.folder.bin_path)?;
+        manifest.upsert(&prepend_just_prefix(&name), url, &commit);
+        manifest.save(&self.folder.bin_path)
+    }
+
+    /// Clone `url` at `reference` into `scratch_dir` (or a fresh default one)
+    /// and build it with cargo. Returns the repository name, the directory
+    /// it was built in, and the commit that ended up checked out.
+    fn clone_and_build(
+        &self,
+        url: &str,
+        reference: &GitRef,
+        scratch_dir: Option<PathBuf>,
+    ) -> BoxedResult<(String, PathBuf, String)> {
+        use duct::cmd;
+        use log::debug;
+
+        let repo = source::get_repository_name(url)?;
+        let repo_path = scratch_dir.unwrap_or_else(|| default_scratch_dir(&repo));
         let cargo_path = repo_path.join("Cargo.toml");
 
-        if repo_path.exists() {
-            debug!("Remove existing {:?}", repo_path);
-            remove_dir_all(&repo_path)?;
-        }
+        let spec = source::clone_spec(url);
+        debug!("Shallow clone {:?} into {:?}", spec, repo_path);
+        let commit = git::clone_shallow(&spec, &repo_path, reference)?;
 
-        debug!("Clone {:?} from git", url);
-        cmd("git", &["clone", &url]).run()?;
         debug!("Build {:?} with cargo", cargo_path);
         cmd(
             "cargo",
@@ -101,26 +182,121 @@ impl<'a> Extension<'a> {
         )
         .run()?;
 
-        let exe_name = format!("{}{}", repo, EXE_SUFFIX);
-        let target_path = repo_path.join("target").join("release").join(&exe_name);
-        let bin_path = self.assemble_path(&repo);
-
-        debug!("Copy {:?} into {:?}", target_path, bin_path);
-
-        copy(&target_path, &bin_path)?;
-        remove_dir_all(&repo_path).map_err(|e| e.into())
+        Ok((repo, repo_path, commit))
     }
 
     pub fn uninstall(&self, name: &str) -> BoxedResult<()> {
         use std::fs::remove_file;
 
         if let Some(path) = self.get_path_of(name) {
-            remove_file(path).map_err(|e| e.into())
-        } else {
-            Ok(()) // Silently ignore this
+            remove_file(path)?;
+        }
+
+        let mut manifest = Manifest::load(&self.folder.bin_path)?;
+        manifest.remove(&prepend_just_prefix(name));
+        manifest.save(&self.folder.bin_path)
+    }
+
+    /// Reinstall every extension recorded in the manifest but missing from
+    /// `bin_path`, pinned to the exact commit it was last installed at.
+    /// Returns the per-extension outcome so one failure doesn't hide the rest.
+    pub fn restore(&self) -> BoxedResult<Vec<(String, BoxedResult<()>)>> {
+        let manifest = Manifest::load(&self.folder.bin_path)?;
+
+        let results = manifest
+            .entries()
+            .iter()
+            .filter(|entry| !self.is_installed(&entry.name))
+            .map(|entry| {
+                let reference = GitRef::Commit(entry.commit.clone());
+                let options = InstallOptions {
+                    scratch_dir: None,
+                    alias: Some(strip_just_prefix(&entry.name)),
+                };
+                (
+                    entry.name.clone(),
+                    self.install_with(&entry.url, &reference, &options),
+                )
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Prune manifest entries whose binary is no longer present in `bin_path`.
+    pub fn sync(&self) -> BoxedResult<()> {
+        let mut manifest = Manifest::load(&self.folder.bin_path)?;
+        manifest.retain(|entry| self.is_installed(&entry.name));
+        manifest.save(&self.folder.bin_path)
+    }
+
+    /// Rebuild `name` from its manifest-recorded source at the latest ref
+    /// and atomically swap the binary in place.
+    pub fn upgrade(&self, name: &str) -> BoxedResult<()> {
+        self.upgrade_ref(name, &GitRef::Default)
+    }
+
+    pub fn upgrade_ref(&self, name: &str, reference: &GitRef) -> BoxedResult<()> {
+        use just_core::result::BoxedErr;
+
+        let prefixed = prepend_just_prefix(name);
+        let manifest = Manifest::load(&self.folder.bin_path)?;
+        let entry = manifest
+            .entries()
+            .iter()
+            .find(|entry| entry.name == prefixed)
+            .cloned();
+
+        match entry {
+            Some(entry) => {
+                self.rebuild_and_swap(&entry.url, reference, &strip_just_prefix(&prefixed))
+            }
+            None => BoxedErr::with(format!("{:?} is not an installed extension", prefixed)),
         }
     }
 
+    /// Upgrade every extension in the manifest, reporting per-extension
+    /// success or failure so one failing rebuild doesn't stop the rest.
+    pub fn upgrade_all(&self) -> BoxedResult<Vec<(String, BoxedResult<()>)>> {
+        let manifest = Manifest::load(&self.folder.bin_path)?;
+
+        let results = manifest
+            .entries()
+            .iter()
+            .map(|entry| {
+                let alias = strip_just_prefix(&entry.name);
+                let result = self.rebuild_and_swap(&entry.url, &GitRef::Default, &alias);
+                (entry.name.clone(), result)
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Rebuild `url` into a scratch directory and, only once the build
+    /// succeeds, rename the new binary over the old one so a failed rebuild
+    /// never leaves `alias` in a broken state.
+    fn rebuild_and_swap(&self, url: &str, reference: &GitRef, alias: &str) -> BoxedResult<()> {
+        use log::debug;
+        use remove_dir_all::remove_dir_all;
+        use std::env::consts::EXE_SUFFIX;
+
+        let (repo, repo_path, commit) = self.clone_and_build(url, reference, None)?;
+
+        let exe_name = format!("{}{}", repo, EXE_SUFFIX);
+        let target_path = repo_path.join("target").join("release").join(&exe_name);
+        let bin_path = self.assemble_path(alias);
+
+        debug!("Swap {:?} into {:?}", target_path, bin_path);
+
+        persist_binary(&target_path, &bin_path)?;
+        remove_dir_all(&repo_path)?;
+
+        let mut manifest = Manifest::load(&self.folder.bin_path)?;
+        manifest.upsert(&prepend_just_prefix(alias), url, &commit);
+        manifest.save(&self.folder.bin_path)
+    }
+
     pub fn list(&self) -> Vec<String> {
         use std::env::consts::EXE_SUFFIX;
         use walkdir::WalkDir;
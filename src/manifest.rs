@@ -0,0 +1,146 @@
+use just_core::result::BoxedResult;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE: &str = "extensions.toml";
+
+/// A single installed extension, as recorded in the manifest: enough to
+/// reinstall it from the same source at the same commit on another machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub url: String,
+    pub commit: String,
+}
+
+/// A record of every extension installed through `Extension`, so a machine
+/// can be reconstructed the way `Cargo.lock` reconstructs a dependency tree.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    extensions: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    fn path(bin_path: &Path) -> PathBuf {
+        bin_path.parent().unwrap_or(bin_path).join(MANIFEST_FILE)
+    }
+
+    pub fn load(bin_path: &Path) -> BoxedResult<Self> {
+        use std::fs::read_to_string;
+
+        let path = Self::path(bin_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = read_to_string(&path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    pub fn save(&self, bin_path: &Path) -> BoxedResult<()> {
+        use std::fs::write;
+
+        let path = Self::path(bin_path);
+        let content = toml::to_string_pretty(self)?;
+        write(&path, content).map_err(|e| e.into())
+    }
+
+    pub fn upsert(&mut self, name: &str, url: &str, commit: &str) {
+        if let Some(entry) = self.extensions.iter_mut().find(|entry| entry.name == name) {
+            entry.url = url.to_string();
+            entry.commit = commit.to_string();
+        } else {
+            self.extensions.push(ManifestEntry {
+                name: name.to_string(),
+                url: url.to_string(),
+                commit: commit.to_string(),
+            });
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.extensions.retain(|entry| entry.name != name);
+    }
+
+    pub fn retain<F: FnMut(&ManifestEntry) -> bool>(&mut self, f: F) {
+        self.extensions.retain(f);
+    }
+
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.extensions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Manifest {
+        let mut manifest = Manifest::default();
+        manifest.upsert("just-foo", "https://github.com/a/foo", "aaa");
+        manifest.upsert("just-bar", "https://github.com/a/bar", "bbb");
+
+        manifest
+    }
+
+    #[test]
+    fn upsert_adds_a_new_entry() {
+        let manifest = sample();
+
+        assert_eq!(manifest.entries().len(), 2);
+        assert_eq!(manifest.entries()[0].name, "just-foo");
+        assert_eq!(manifest.entries()[1].name, "just-bar");
+    }
+
+    #[test]
+    fn upsert_updates_the_existing_entry_instead_of_duplicating_it() {
+        let mut manifest = sample();
+        manifest.upsert("just-foo", "https://github.com/a/foo", "ccc");
+
+        assert_eq!(manifest.entries().len(), 2);
+
+        let entry = manifest
+            .entries()
+            .iter()
+            .find(|entry| entry.name == "just-foo")
+            .unwrap();
+        assert_eq!(entry.commit, "ccc");
+    }
+
+    #[test]
+    fn remove_drops_only_the_named_entry() {
+        let mut manifest = sample();
+        manifest.remove("just-foo");
+
+        assert_eq!(manifest.entries().len(), 1);
+        assert_eq!(manifest.entries()[0].name, "just-bar");
+    }
+
+    #[test]
+    fn remove_is_a_no_op_for_an_unknown_name() {
+        let mut manifest = sample();
+        manifest.remove("just-unknown");
+
+        assert_eq!(manifest.entries().len(), 2);
+    }
+
+    #[test]
+    fn retain_keeps_only_matching_entries() {
+        let mut manifest = sample();
+        manifest.retain(|entry| entry.name == "just-bar");
+
+        assert_eq!(manifest.entries().len(), 1);
+        assert_eq!(manifest.entries()[0].name, "just-bar");
+    }
+
+    #[test]
+    fn path_is_in_bin_paths_parent_directory() {
+        let bin_path = Path::new("/home/user/.just/bin");
+
+        assert_eq!(
+            Manifest::path(bin_path),
+            Path::new("/home/user/.just/extensions.toml")
+        );
+    }
+}
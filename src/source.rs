@@ -0,0 +1,166 @@
+use just_core::result::BoxedResult;
+use url::Url;
+
+/// The forge hosting an extension's repository. `just-extension` used to be
+/// hardwired to github.com; this lets it install from any remote.
+#[derive(Debug, PartialEq, Eq)]
+pub enum GitHost {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    Generic,
+}
+
+impl GitHost {
+    fn detect(url: &Url) -> Self {
+        match url.host_str() {
+            Some("github.com") => GitHost::GitHub,
+            Some("bitbucket.org") => GitHost::Bitbucket,
+            Some(host) if host == "gitlab.com" || host.starts_with("gitlab.") => GitHost::GitLab,
+            _ => GitHost::Generic,
+        }
+    }
+
+    /// The repository name to install under: the last non-empty path
+    /// segment, with any `.git` suffix stripped. Works the same way for a
+    /// flat `owner/repo` layout and a nested GitLab `group/subgroup/project`
+    /// one, since both just need the final segment.
+    fn repository_name(&self, url: &Url) -> BoxedResult<String> {
+        use just_core::result::BoxedErr;
+
+        let segment = url
+            .path_segments()
+            .and_then(|segments| segments.filter(|segment| !segment.is_empty()).last());
+
+        match segment {
+            Some(name) => Ok(name.trim_end_matches(".git").to_string()),
+            None => BoxedErr::with("No repository name in URL"),
+        }
+    }
+}
+
+/// Normalize an `scp`-style remote (`git@host:owner/repo.git`) into a URL
+/// `Url::parse` understands. URLs that already have a scheme pass through.
+fn normalize(url: &str) -> String {
+    if url.contains("://") || !url.contains('@') {
+        return url.to_string();
+    }
+
+    match url
+        .split_once('@')
+        .and_then(|(_, rest)| rest.split_once(':'))
+    {
+        Some((host, path)) => format!("ssh://git@{}/{}", host, path),
+        None => url.to_string(),
+    }
+}
+
+/// The clone spec to hand to `git::clone_shallow`: the original URL, or its
+/// normalized form if it was `scp`-style.
+pub fn clone_spec(url: &str) -> String {
+    normalize(url)
+}
+
+pub fn get_repository_name(url: &str) -> BoxedResult<String> {
+    let parsed = Url::parse(&normalize(url))?;
+    let host = GitHost::detect(&parsed);
+
+    host.repository_name(&parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repository_name_from_github_url() {
+        assert_eq!(
+            get_repository_name("https://github.com/owner/repo").unwrap(),
+            "repo"
+        );
+    }
+
+    #[test]
+    fn repository_name_strips_dot_git_suffix() {
+        assert_eq!(
+            get_repository_name("https://github.com/owner/repo.git").unwrap(),
+            "repo"
+        );
+    }
+
+    #[test]
+    fn repository_name_from_nested_gitlab_group() {
+        assert_eq!(
+            get_repository_name("https://gitlab.com/group/subgroup/project").unwrap(),
+            "project"
+        );
+    }
+
+    #[test]
+    fn repository_name_from_self_hosted_gitlab() {
+        assert_eq!(
+            get_repository_name("https://gitlab.example.com/group/project.git").unwrap(),
+            "project"
+        );
+    }
+
+    #[test]
+    fn repository_name_from_bitbucket_url() {
+        assert_eq!(
+            get_repository_name("https://bitbucket.org/owner/repo").unwrap(),
+            "repo"
+        );
+    }
+
+    #[test]
+    fn repository_name_from_generic_remote() {
+        assert_eq!(
+            get_repository_name("https://git.example.com/owner/repo.git").unwrap(),
+            "repo"
+        );
+    }
+
+    #[test]
+    fn repository_name_from_scp_style_url() {
+        assert_eq!(
+            get_repository_name("git@github.com:owner/repo.git").unwrap(),
+            "repo"
+        );
+    }
+
+    #[test]
+    fn clone_spec_normalizes_scp_style_url() {
+        assert_eq!(
+            clone_spec("git@github.com:owner/repo.git"),
+            "ssh://git@github.com/owner/repo.git"
+        );
+    }
+
+    #[test]
+    fn clone_spec_passes_through_urls_with_a_scheme() {
+        assert_eq!(
+            clone_spec("https://github.com/owner/repo.git"),
+            "https://github.com/owner/repo.git"
+        );
+    }
+
+    #[test]
+    fn host_detection() {
+        assert_eq!(
+            GitHost::detect(&Url::parse("https://github.com/owner/repo").unwrap()),
+            GitHost::GitHub
+        );
+        assert_eq!(
+            GitHost::detect(&Url::parse("https://gitlab.com/owner/repo").unwrap()),
+            GitHost::GitLab
+        );
+        assert_eq!(
+            GitHost::detect(&Url::parse("https://bitbucket.org/owner/repo").unwrap()),
+            GitHost::Bitbucket
+        );
+        assert_eq!(
+            GitHost::detect(&Url::parse("https://git.example.com/owner/repo").unwrap()),
+            GitHost::Generic
+        );
+    }
+}